@@ -0,0 +1,58 @@
+use ark_bls12_381::{g1, G1Affine};
+use ark_ec::AffineCurve;
+use ark_std::UniformRand;
+use arkmsm_learn::batch_adder::BatchAdder;
+use criterion::{criterion_group, criterion_main, Criterion};
+
+#[cfg(feature = "parallel")]
+use arkmsm_learn::batch_adder::batch_add_parallel;
+
+const BATCH_SIZE: usize = 1 << 16;
+
+#[cfg(feature = "parallel")]
+const PARALLEL_CHUNK_SIZE: usize = 1 << 10;
+
+fn random_points(n: usize) -> Vec<G1Affine> {
+    let mut rng = ark_std::test_rng();
+    (0..n)
+        .map(|_| G1Affine::from(<G1Affine as AffineCurve>::Projective::rand(&mut rng)))
+        .collect()
+}
+
+fn bench_batch_add(c: &mut Criterion) {
+    let dest = random_points(BATCH_SIZE);
+    let src = random_points(BATCH_SIZE);
+
+    c.bench_function("batch_add 2^16", |b| {
+        let mut adder: BatchAdder<g1::Parameters> = BatchAdder::new(BATCH_SIZE);
+        b.iter(|| {
+            let mut dest = dest.clone();
+            adder.batch_add(&mut dest, &src);
+        })
+    });
+
+    c.bench_function("batch_add_nonexceptional 2^16", |b| {
+        let mut adder: BatchAdder<g1::Parameters> = BatchAdder::new(BATCH_SIZE);
+        b.iter(|| {
+            let mut dest = dest.clone();
+            adder.batch_add_nonexceptional(&mut dest, &src);
+        })
+    });
+
+    #[cfg(feature = "parallel")]
+    for threads in [1, 2, 4, 8] {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap();
+        c.bench_function(&format!("batch_add_parallel 2^16, {} threads", threads), |b| {
+            b.iter(|| {
+                let mut dest = dest.clone();
+                pool.install(|| batch_add_parallel(&mut dest, &src, PARALLEL_CHUNK_SIZE));
+            })
+        });
+    }
+}
+
+criterion_group!(benches, bench_batch_add);
+criterion_main!(benches);
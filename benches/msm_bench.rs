@@ -0,0 +1,41 @@
+use ark_bls12_381::{Fr, G1Affine};
+use ark_ec::AffineCurve;
+use ark_std::UniformRand;
+use arkmsm_learn::msm::msm;
+use criterion::{criterion_group, criterion_main, Criterion};
+
+#[cfg(feature = "parallel")]
+use arkmsm_learn::msm::msm_parallel;
+
+const SIZE: usize = 1 << 16;
+
+fn random_inputs() -> (Vec<G1Affine>, Vec<Fr>) {
+    let mut rng = ark_std::test_rng();
+    let points = (0..SIZE)
+        .map(|_| G1Affine::from(<G1Affine as AffineCurve>::Projective::rand(&mut rng)))
+        .collect();
+    let scalars = (0..SIZE).map(|_| Fr::rand(&mut rng)).collect();
+    (points, scalars)
+}
+
+fn bench_msm(c: &mut Criterion) {
+    let (points, scalars) = random_inputs();
+
+    c.bench_function("msm serial 2^16", |b| {
+        b.iter(|| msm(&points, &scalars));
+    });
+
+    #[cfg(feature = "parallel")]
+    for threads in [1, 2, 4, 8] {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .unwrap();
+        c.bench_function(&format!("msm_parallel 2^16, {} threads", threads), |b| {
+            b.iter(|| pool.install(|| msm_parallel(&points, &scalars)));
+        });
+    }
+}
+
+criterion_group!(benches, bench_msm);
+criterion_main!(benches);
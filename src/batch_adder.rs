@@ -1,5 +1,5 @@
-use ark_bls12_381::G1Affine;
-use ark_ec::{AffineCurve};
+use ark_ec::short_weierstrass_jacobian::GroupAffine;
+use ark_ec::SWModelParameters;
 use ark_ff::Field;
 use ark_std::{One, Zero};
 // Batch Addition for Multi-Scalar Multiplication
@@ -11,22 +11,27 @@ use ark_std::{One, Zero};
 // R.y = k(P.x-R.x)-P.y
 
 //参考https://hackmd.io/1mpavmFmQNWrahBi8mHBjQ
+//
+// Generic over any short-Weierstrass curve `P` (BLS12-381 G1, G2, BN254,
+// Pallas/Vesta, ...), so the `a` coefficient of the curve equation can no
+// longer be assumed zero - it's threaded through the doubling slope in
+// `batch_add_phase_two` as `P::COEFF_A`.
 #[derive(Debug)]
-pub struct BatchAdder {
-    inverse_state: <G1Affine as AffineCurve>::BaseField,
-    inverses: Vec<<G1Affine as AffineCurve>::BaseField>,
+pub struct BatchAdder<P: SWModelParameters> {
+    inverse_state: P::BaseField,
+    inverses: Vec<P::BaseField>,
 }
 
-impl BatchAdder {
+impl<P: SWModelParameters> BatchAdder<P> {
     pub fn new(max_batch_cnt: usize) -> Self {
         BatchAdder {
-            inverse_state: <G1Affine as AffineCurve>::BaseField::one(),
-            inverses: vec![<G1Affine as AffineCurve>::BaseField::one(); max_batch_cnt],
+            inverse_state: P::BaseField::one(),
+            inverses: vec![P::BaseField::one(); max_batch_cnt],
         }
     }
 
     /// Batch add vector dest and src, the results will be stored in dest, i.e. dest[i] = dest[i] + src[i]
-    pub fn batch_add(&mut self, dest: &mut [G1Affine], src: &[G1Affine]) {
+    pub fn batch_add(&mut self, dest: &mut [GroupAffine<P>], src: &[GroupAffine<P>]) {
         assert!(dest.len() == src.len(), "length of dest and src don't match!");
         assert!(dest.len() <= self.inverses.len(),
                 "input length exceeds the max_batch_cnt, please increase max_batch_cnt during initialization!");
@@ -44,9 +49,9 @@ impl BatchAdder {
     /// Batch add vector dest and src of len entries, skipping dest_step and src_step entries each
     /// the results will be stored in dest, i.e. dest[i] = dest[i] + src[i]
     pub fn batch_add_step_n(&mut self,
-                            dest: &mut [G1Affine],
+                            dest: &mut [GroupAffine<P>],
                             dest_step: usize,
-                            src: &[G1Affine],
+                            src: &[GroupAffine<P>],
                             src_step: usize,
                             len: usize) {
         assert!(dest.len() > (len - 1) * dest_step, "insufficient entries in dest array");
@@ -81,14 +86,14 @@ impl BatchAdder {
     ///      - slope s and ss from state
     ///      - inverse_state = inverse_state * deltaX
     ///      - addition result acc
-    /// 以i为界限 设λi = Qxi-Pxi; 
+    /// 以i为界限 设λi = Qxi-Pxi;
     /// 先计算λi左边，即λ1~i-1连乘结果,不含λi
     /// 再计算λi右边，即λi+1~n的连乘结果，不含λi
-    /// 
+    ///
     pub fn batch_add_phase_one(
             &mut self,
-            p: &G1Affine,
-            q: &G1Affine,
+            p: &GroupAffine<P>,
+            q: &GroupAffine<P>,
             idx: usize,
         ) {
         assert!(idx < self.inverses.len(),
@@ -123,8 +128,8 @@ impl BatchAdder {
 
     pub fn batch_add_phase_two(
             &mut self,
-            p: &mut G1Affine,
-            q: &G1Affine,
+            p: &mut GroupAffine<P>,
+            q: &GroupAffine<P>,
             idx: usize,
         ) {
         assert!(idx < self.inverses.len(),
@@ -149,11 +154,12 @@ impl BatchAdder {
                 return;
             }
             // Otherwise, p = q, and it's point doubling
-            // Processing is almost the same, except s=3*affine.x^2 / 2*affine.y
+            // Processing is almost the same, except s=(3*affine.x^2+a) / 2*affine.y
 
-            // set delta_y = 3*q.x^2
+            // set delta_y = 3*q.x^2 + a
             delta_y = q.x.square();
             delta_y = delta_y + delta_y + delta_y;
+            delta_y += P::COEFF_A;
 
             delta_x = q.y.double();
         }
@@ -168,18 +174,150 @@ impl BatchAdder {
         p.y = s * delta_x;
         p.y = p.y - q.y;
     }
-} 
+
+    /// Fast path for the overwhelmingly common "generic P+Q" case: every
+    /// pair in the batch must already be known non-identity and neither
+    /// equal nor negations of one another (`debug_assert!`-ed, not
+    /// checked), so phase one is a straight deltaX accumulation and phase
+    /// two is pure slope arithmetic with no conditionals at all. Callers
+    /// should pre-filter with `partition_exceptional` (or equivalent) and
+    /// run the handful of exceptional pairs through `batch_add` instead.
+    pub fn batch_add_nonexceptional(&mut self, dest: &mut [GroupAffine<P>], src: &[GroupAffine<P>]) {
+        assert!(dest.len() == src.len(), "length of dest and src don't match!");
+        assert!(dest.len() <= self.inverses.len(),
+                "input length exceeds the max_batch_cnt, please increase max_batch_cnt during initialization!");
+
+        self.reset();
+        for i in 0..dest.len() {
+            self.nonexceptional_phase_one(&dest[i], &src[i], i);
+        }
+        self.inverse();
+        for i in (0..dest.len()).rev() {
+            self.nonexceptional_phase_two(&mut dest[i], &src[i], i);
+        }
+    }
+
+    fn nonexceptional_phase_one(&mut self, p: &GroupAffine<P>, q: &GroupAffine<P>, idx: usize) {
+        debug_assert!(!p.is_zero() && !q.is_zero(),
+                "batch_add_nonexceptional requires non-identity points");
+        let delta_x = q.x - p.x;
+        debug_assert!(!delta_x.is_zero(),
+                "batch_add_nonexceptional requires p != q and p != -q");
+
+        if self.inverse_state.is_zero() {
+            self.inverses[idx].set_one();
+            self.inverse_state = delta_x;
+        } else {
+            self.inverses[idx] = self.inverse_state;
+            self.inverse_state *= delta_x;
+        }
+    }
+
+    fn nonexceptional_phase_two(&mut self, p: &mut GroupAffine<P>, q: &GroupAffine<P>, idx: usize) {
+        let mut _inverse = self.inverses[idx];
+        _inverse *= self.inverse_state;
+
+        let delta_x = q.x - p.x;
+        let delta_y = q.y - p.y;
+        debug_assert!(!delta_x.is_zero(),
+                "batch_add_nonexceptional requires p != q and p != -q");
+
+        self.inverse_state *= delta_x;
+
+        let s = delta_y * _inverse;
+        let ss = s * s;
+        p.x = ss - q.x - p.x;
+        let delta_x = q.x - p.x;
+        p.y = s * delta_x - q.y;
+    }
+}
+
+/// Parallel counterpart of `batch_add_fast`: splits `(dest, src)` into
+/// `chunk_size`-sized chunks and runs each chunk's two-pass batch add on a
+/// rayon thread pool. Each chunk gets its own `BatchAdder` scratch space, so
+/// the Montgomery batch inversion stays local to a chunk and no thread ever
+/// touches another thread's `inverse_state`/`inverses`.
+#[cfg(feature = "parallel")]
+pub fn batch_add_parallel<P: SWModelParameters + Send + Sync>(
+    dest: &mut [GroupAffine<P>],
+    src: &[GroupAffine<P>],
+    chunk_size: usize,
+) where
+    P::BaseField: Send + Sync,
+{
+    use rayon::prelude::*;
+
+    assert!(dest.len() == src.len(), "length of dest and src don't match!");
+
+    dest.par_chunks_mut(chunk_size)
+        .zip(src.par_chunks(chunk_size))
+        .for_each(|(dest_chunk, src_chunk)| {
+            let mut adder: BatchAdder<P> = BatchAdder::new(dest_chunk.len());
+            adder.batch_add_fast(dest_chunk, src_chunk);
+        });
+}
+
+/// Splits a `(dest, src)` batch into the indices safe for
+/// `BatchAdder::batch_add_nonexceptional` ("clean": neither side is the
+/// identity and `p.x != q.x`, so they're neither equal nor negations of
+/// each other) and the handful of exceptional indices that need the
+/// general, branchy `batch_add` path.
+pub fn partition_exceptional<P: SWModelParameters>(
+    dest: &[GroupAffine<P>],
+    src: &[GroupAffine<P>],
+) -> (Vec<usize>, Vec<usize>) {
+    assert!(dest.len() == src.len(), "length of dest and src don't match!");
+
+    let mut clean = Vec::with_capacity(dest.len());
+    let mut exceptional = Vec::new();
+    for i in 0..dest.len() {
+        if dest[i].is_zero() || src[i].is_zero() || dest[i].x == src[i].x {
+            exceptional.push(i);
+        } else {
+            clean.push(i);
+        }
+    }
+    (clean, exceptional)
+}
+
+impl<P: SWModelParameters> BatchAdder<P> {
+    /// `batch_add`, but routes the clean majority of the batch through the
+    /// branch-free `batch_add_nonexceptional` path and only the handful of
+    /// identity/doubling/negation pairs through the general path.
+    pub fn batch_add_fast(&mut self, dest: &mut [GroupAffine<P>], src: &[GroupAffine<P>]) {
+        let (clean, exceptional) = partition_exceptional(dest, src);
+
+        if !clean.is_empty() {
+            let mut clean_dest: Vec<_> = clean.iter().map(|&i| dest[i]).collect();
+            let clean_src: Vec<_> = clean.iter().map(|&i| src[i]).collect();
+            self.batch_add_nonexceptional(&mut clean_dest, &clean_src);
+            for (&i, v) in clean.iter().zip(clean_dest) {
+                dest[i] = v;
+            }
+        }
+
+        if !exceptional.is_empty() {
+            let mut exc_dest: Vec<_> = exceptional.iter().map(|&i| dest[i]).collect();
+            let exc_src: Vec<_> = exceptional.iter().map(|&i| src[i]).collect();
+            self.batch_add(&mut exc_dest, &exc_src);
+            for (&i, v) in exceptional.iter().zip(exc_dest) {
+                dest[i] = v;
+            }
+        }
+    }
+}
 
 #[cfg(test)]
 mod batch_add_tests {
     use super::*;
-    use ark_ec::ProjectiveCurve;
+    use ark_bls12_381::{g1, g2, G1Affine, G2Affine};
+    use ark_ec::{AffineCurve, ProjectiveCurve};
     use ark_std::UniformRand;
     use std::ops::Add;
 
     #[test]
     fn test_phase_one_zero_or_neg() {
-        let mut batch_adder = BatchAdder::new(4);
+        let mut batch_adder: BatchAdder<g1::Parameters> = BatchAdder::new(4);
         batch_adder.batch_add_phase_one(
             &G1Affine::zero(),
             &G1Affine::zero(),
@@ -201,7 +339,7 @@ mod batch_add_tests {
 
     #[test]
     fn test_phase_one_p_add_p() {
-        let mut batch_adder = BatchAdder::new(4);
+        let mut batch_adder: BatchAdder<g1::Parameters> = BatchAdder::new(4);
         let mut rng = ark_std::test_rng();
         let prj = <G1Affine as AffineCurve>::Projective::rand(&mut rng);
         let p = G1Affine::from(prj);
@@ -214,7 +352,7 @@ mod batch_add_tests {
 
     #[test]
     fn test_phase_one_p_add_q() {
-        let mut batch_adder = BatchAdder::new(4);
+        let mut batch_adder: BatchAdder<g1::Parameters> = BatchAdder::new(4);
         let mut rng = ark_std::test_rng();
         let p_prj = <G1Affine as AffineCurve>::Projective::rand(&mut rng);
         let q_prj = <G1Affine as AffineCurve>::Projective::rand(&mut rng);
@@ -228,7 +366,7 @@ mod batch_add_tests {
 
     #[test]
     fn test_phase_one_p_add_q_twice() {
-        let mut batch_adder = BatchAdder::new(4);
+        let mut batch_adder: BatchAdder<g1::Parameters> = BatchAdder::new(4);
         let mut rng = ark_std::test_rng();
         let p_prj = <G1Affine as AffineCurve>::Projective::rand(&mut rng);
         let q_prj = <G1Affine as AffineCurve>::Projective::rand(&mut rng);
@@ -243,7 +381,7 @@ mod batch_add_tests {
 
     #[test]
     fn test_phase_two_zero_add_p() {
-        let mut batch_adder = BatchAdder::new(4);
+        let mut batch_adder: BatchAdder<g1::Parameters> = BatchAdder::new(4);
         let mut rng = ark_std::test_rng();
         let p_prj = <G1Affine as AffineCurve>::Projective::rand(&mut rng);
         let p = G1Affine::from(p_prj);
@@ -254,7 +392,7 @@ mod batch_add_tests {
 
     #[test]
     fn test_phase_two_p_add_neg() {
-        let mut batch_adder = BatchAdder::new(4);
+        let mut batch_adder: BatchAdder<g1::Parameters> = BatchAdder::new(4);
 
         let mut rng = ark_std::test_rng();
         let p_prj = <G1Affine as AffineCurve>::Projective::rand(&mut rng);
@@ -268,7 +406,7 @@ mod batch_add_tests {
 
     #[test]
     fn test_phase_two_p_add_q() {
-        let mut batch_adder = BatchAdder::new(4);
+        let mut batch_adder: BatchAdder<g1::Parameters> = BatchAdder::new(4);
 
         let mut rng = ark_std::test_rng();
         let acc_prj = <G1Affine as AffineCurve>::Projective::rand(&mut rng);
@@ -283,7 +421,7 @@ mod batch_add_tests {
 
     #[test]
     fn test_phase_two_p_add_p() {
-        let mut batch_adder = BatchAdder::new(4);
+        let mut batch_adder: BatchAdder<g1::Parameters> = BatchAdder::new(4);
 
         let mut rng = ark_std::test_rng();
         let acc_prj = <G1Affine as AffineCurve>::Projective::rand(&mut rng);
@@ -297,7 +435,7 @@ mod batch_add_tests {
 
     #[test]
     fn test_batch_add() {
-        let mut batch_adder = BatchAdder::new(10);
+        let mut batch_adder: BatchAdder<g1::Parameters> = BatchAdder::new(10);
 
         let mut rng = ark_std::test_rng();
         let mut buckets: Vec<G1Affine> = (0..10)
@@ -317,7 +455,7 @@ mod batch_add_tests {
 
     #[test]
     fn test_batch_add_step_n() {
-        let mut batch_adder = BatchAdder::new(10);
+        let mut batch_adder: BatchAdder<g1::Parameters> = BatchAdder::new(10);
 
         let mut rng = ark_std::test_rng();
         let mut buckets: Vec<G1Affine> = (0..10)
@@ -334,4 +472,167 @@ mod batch_add_tests {
             assert_eq!(buckets[i], tmp[i].add(points[i * 2]));
         }
     }
-}
\ No newline at end of file
+
+    // test_batch_add and test_phase_two_p_add_p again, but on G2, to prove BatchAdder isn't secretly
+    // still tied to G1. BLS12-381's G1 and G2 both have COEFF_A = 0, so this
+    // doesn't exercise the nonzero-`a` doubling term - see
+    // ToyCoeffAParameters below for that.
+    #[test]
+    fn test_batch_add_g2() {
+        let mut batch_adder: BatchAdder<g2::Parameters> = BatchAdder::new(10);
+
+        let mut rng = ark_std::test_rng();
+        let mut buckets: Vec<G2Affine> = (0..10)
+            .map(|_| G2Affine::from(<G2Affine as AffineCurve>::Projective::rand(&mut rng)))
+            .collect();
+        let points: Vec<G2Affine> = (0..10)
+            .map(|_| G2Affine::from(<G2Affine as AffineCurve>::Projective::rand(&mut rng)))
+            .collect();
+
+        let tmp = buckets.clone();
+        batch_adder.batch_add(&mut buckets, &points);
+
+        for i in 0..10 {
+            assert_eq!(buckets[i], tmp[i].add(points[i]));
+        }
+    }
+
+    #[test]
+    fn test_phase_two_p_add_p_g2() {
+        let mut batch_adder: BatchAdder<g2::Parameters> = BatchAdder::new(4);
+
+        let mut rng = ark_std::test_rng();
+        let acc_prj = <G2Affine as AffineCurve>::Projective::rand(&mut rng);
+        let mut acc = G2Affine::from(acc_prj);
+        let p = acc.clone();
+
+        batch_adder.inverses[0] = (p.y + p.y).inverse().unwrap();
+        batch_adder.batch_add_phase_two(&mut acc, &p, 0);
+        assert_eq!(acc, G2Affine::from(acc_prj).add(p));
+    }
+
+    // A toy curve with COEFF_A != 0, unlike every real BLS12-381 curve this
+    // module is otherwise tested against - so the `P::COEFF_A` term added to
+    // `batch_add_phase_two`'s doubling slope actually gets exercised instead
+    // of always multiplying by zero. Reuses BLS12-381's Fq/Fr purely for
+    // their field arithmetic; the group structure (cofactor, generator) is
+    // unrelated to BLS12-381 and made up to satisfy `y^2 = x^3 + x + 1`.
+    use ark_bls12_381::{Fq, Fr};
+    use ark_ec::ModelParameters;
+    use ark_ff::field_new;
+
+    #[derive(Clone, Default, PartialEq, Eq, Debug)]
+    struct ToyCoeffAParameters;
+
+    impl ModelParameters for ToyCoeffAParameters {
+        type BaseField = Fq;
+        type ScalarField = Fr;
+    }
+
+    impl SWModelParameters for ToyCoeffAParameters {
+        const COEFF_A: Fq = field_new!(Fq, "1");
+        const COEFF_B: Fq = field_new!(Fq, "1");
+        const COFACTOR: &'static [u64] = &[1];
+        const COFACTOR_INV: Fr = field_new!(Fr, "1");
+        // (0, 1) satisfies y^2 = x^3 + x + 1 since 1 = 0 + 0 + 1.
+        const AFFINE_GENERATOR_COEFFS: (Self::BaseField, Self::BaseField) =
+            (field_new!(Fq, "0"), field_new!(Fq, "1"));
+    }
+
+    type ToyAffine = GroupAffine<ToyCoeffAParameters>;
+    type ToyProjective = <ToyAffine as AffineCurve>::Projective;
+
+    #[test]
+    fn test_phase_two_p_add_p_nonzero_coeff_a() {
+        let mut batch_adder: BatchAdder<ToyCoeffAParameters> = BatchAdder::new(4);
+
+        let mut rng = ark_std::test_rng();
+        let acc_prj = ToyProjective::rand(&mut rng);
+        let mut acc = ToyAffine::from(acc_prj);
+        let p = acc.clone();
+
+        batch_adder.inverses[0] = (p.y + p.y).inverse().unwrap();
+        batch_adder.batch_add_phase_two(&mut acc, &p, 0);
+        assert_eq!(acc, ToyAffine::from(acc_prj).add(p));
+    }
+
+    #[test]
+    fn test_batch_add_nonzero_coeff_a() {
+        let mut batch_adder: BatchAdder<ToyCoeffAParameters> = BatchAdder::new(10);
+
+        let mut rng = ark_std::test_rng();
+        let mut buckets: Vec<ToyAffine> = (0..10)
+            .map(|_| ToyAffine::from(ToyProjective::rand(&mut rng)))
+            .collect();
+        let points: Vec<ToyAffine> = (0..10)
+            .map(|_| ToyAffine::from(ToyProjective::rand(&mut rng)))
+            .collect();
+
+        let tmp = buckets.clone();
+        batch_adder.batch_add(&mut buckets, &points);
+
+        for i in 0..10 {
+            assert_eq!(buckets[i], tmp[i].add(points[i]));
+        }
+    }
+
+    #[test]
+    fn test_batch_add_nonexceptional_matches_batch_add() {
+        let mut batch_adder: BatchAdder<g1::Parameters> = BatchAdder::new(10);
+        let mut fast_adder: BatchAdder<g1::Parameters> = BatchAdder::new(10);
+
+        let mut rng = ark_std::test_rng();
+        let mut buckets: Vec<G1Affine> = (0..10)
+            .map(|_| G1Affine::from(<G1Affine as AffineCurve>::Projective::rand(&mut rng)))
+            .collect();
+        let points: Vec<G1Affine> = (0..10)
+            .map(|_| G1Affine::from(<G1Affine as AffineCurve>::Projective::rand(&mut rng)))
+            .collect();
+
+        let mut expected = buckets.clone();
+        batch_adder.batch_add(&mut expected, &points);
+
+        fast_adder.batch_add_nonexceptional(&mut buckets, &points);
+        assert_eq!(buckets, expected);
+    }
+
+    #[test]
+    fn test_batch_add_fast_handles_exceptional_pairs() {
+        let mut rng = ark_std::test_rng();
+        let generic = G1Affine::from(<G1Affine as AffineCurve>::Projective::rand(&mut rng));
+        let other = G1Affine::from(<G1Affine as AffineCurve>::Projective::rand(&mut rng));
+        let mut neg_other = other;
+        neg_other.y = -neg_other.y;
+
+        let mut buckets = vec![generic, G1Affine::zero(), other, generic];
+        let points = vec![other, other, other, neg_other];
+
+        let mut expected = buckets.clone();
+        let mut reference_adder: BatchAdder<g1::Parameters> = BatchAdder::new(4);
+        reference_adder.batch_add(&mut expected, &points);
+
+        let mut fast_adder: BatchAdder<g1::Parameters> = BatchAdder::new(4);
+        fast_adder.batch_add_fast(&mut buckets, &points);
+        assert_eq!(buckets, expected);
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_batch_add_parallel_matches_serial() {
+        let mut rng = ark_std::test_rng();
+        let buckets: Vec<G1Affine> = (0..100)
+            .map(|_| G1Affine::from(<G1Affine as AffineCurve>::Projective::rand(&mut rng)))
+            .collect();
+        let points: Vec<G1Affine> = (0..100)
+            .map(|_| G1Affine::from(<G1Affine as AffineCurve>::Projective::rand(&mut rng)))
+            .collect();
+
+        let mut expected = buckets.clone();
+        let mut reference_adder: BatchAdder<g1::Parameters> = BatchAdder::new(100);
+        reference_adder.batch_add(&mut expected, &points);
+
+        let mut actual = buckets.clone();
+        super::batch_add_parallel(&mut actual, &points, 16);
+        assert_eq!(actual, expected);
+    }
+}
@@ -0,0 +1,260 @@
+use ark_bls12_381::{Fq, Fr, G1Affine};
+use ark_ff::{BigInteger384, FpParameters, PrimeField};
+use std::cmp::Ordering;
+
+// GLV endomorphism decomposition for BLS12-381 G1
+//
+// The curve has a nontrivial endomorphism phi(x, y) = (beta*x, y), where beta
+// is a primitive cube root of unity in Fq. phi acts on G1 as scalar
+// multiplication by lambda, a cube root of unity mod r (the scalar field
+// order). That lets us split any 255-bit scalar k into two ~128-bit
+// half-scalars k1, k2 with k = k1 + k2*lambda (mod r), so
+//   k*P = k1*P + k2*phi(P)
+// which would let an MSM halve its window count by running both halves
+// through the same bucket method. This module is the scalar-decomposition
+// primitive on its own - `msm()` doesn't call it yet.
+//
+// lambda is the unique nontrivial root of x^2+x+1 = 0 mod r such that
+// lambda < sqrt(r); see https://hackmd.io/@yelhousni/bls12_381 for the
+// derivation. For BLS12-381 specifically, lambda happens to land within a
+// single bit of sqrt(r), which collapses the usual lattice-basis-reduction
+// step (Algorithm 3.74, Guide to Elliptic Curve Cryptography) down to one
+// division: writing r = Q*lambda + 1 (the remainder is exactly 1), the two
+// vectors
+//   v1 = (lambda, -1)
+//   v2 = (1, Q)
+// already form a determinant-r basis of the lattice {(u,v) : u+v*lambda = 0 mod r}.
+
+/// Eigenvalue of `phi` in the scalar field: `lambda^2 + lambda + 1 = 0 mod r`.
+const LAMBDA: u128 = 0xac45a4010001a40200000000ffffffff;
+
+/// `r / LAMBDA` (exact integer division, remainder 1). Forms the lattice
+/// basis vector `(1, Q)` alongside `(LAMBDA, -1)`.
+const Q: u128 = 0xac45a4010001a4020000000100000000;
+
+/// Cube root of unity in Fq with `beta^2 + beta + 1 = 0`, used by `phi`. Of
+/// the two roots of that equation, this is specifically the one that makes
+/// `phi` agree with scalar multiplication by `LAMBDA` (the other root gives
+/// `phi(P) = LAMBDA^2 * P` instead).
+fn beta() -> Fq {
+    Fq::from_repr(BigInteger384::new([
+        0x8bfd00000000aaac,
+        0x409427eb4f49fffd,
+        0x897d29650fb85f9b,
+        0xaa0d857d89759ad4,
+        0xec02408663d4de85,
+        0x1a0111ea397fe699,
+    ]))
+    .unwrap()
+}
+
+/// `phi(P) = (beta*P.x, P.y)`. Scales `P` by `LAMBDA` without touching the
+/// scalar field, so it's far cheaper than a real scalar multiplication.
+pub fn endomorphism(p: &G1Affine) -> G1Affine {
+    G1Affine::new(p.x * beta(), p.y, p.infinity)
+}
+
+/// Split `k` into `(sign1, k1, sign2, k2)` such that
+/// `k = (sign1 ? -k1 : k1) + (sign2 ? -k2 : k2)*LAMBDA (mod r)`, with `k1` and
+/// `k2` each bounded by roughly `sqrt(r)` (comfortably under 2^128).
+pub fn decompose(k: &Fr) -> (bool, u128, bool, u128) {
+    let r = <Fr as PrimeField>::Params::MODULUS.0;
+    let k_limbs = k.into_repr().0;
+
+    // c2 = round(k / r): k < r, so this is 0 unless k is in the top half of
+    // the field, in which case it's 1.
+    let c2 = if ge6(&shl1_6(&zero_extend(&k_limbs)), &zero_extend(&r)) {
+        1u128
+    } else {
+        0u128
+    };
+
+    // c1 = round(Q*k / r), computed over a wide (384-bit) intermediate since
+    // Q*k can be up to ~383 bits.
+    let product = mul_u128_u256(Q, &k_limbs);
+    let (c1, remainder) = divmod_wide(product, &r);
+    let c1 = if ge6(&shl1_6(&zero_extend(&remainder)), &zero_extend(&r)) {
+        c1 + 1
+    } else {
+        c1
+    };
+
+    // k1 = k - c1*LAMBDA - c2
+    let lambda_limbs = [LAMBDA as u64, (LAMBDA >> 64) as u64, 0, 0];
+    let mut subtrahend = mul_u128_u256(c1, &lambda_limbs);
+    add6_small(&mut subtrahend, c2 as u64);
+    let (sign1, k1_wide) = signed_sub6(&zero_extend(&k_limbs), &subtrahend);
+    let k1 = to_u128(&k1_wide);
+
+    // k2 = c1 - c2*Q
+    let (sign2, k2) = if c2 == 0 {
+        (false, c1)
+    } else if c1 >= Q {
+        (false, c1 - Q)
+    } else {
+        (true, Q - c1)
+    };
+
+    (sign1, k1, sign2, k2)
+}
+
+fn zero_extend(limbs: &[u64; 4]) -> [u64; 6] {
+    [limbs[0], limbs[1], limbs[2], limbs[3], 0, 0]
+}
+
+fn to_u128(limbs: &[u64; 6]) -> u128 {
+    debug_assert!(limbs[2] == 0 && limbs[3] == 0 && limbs[4] == 0 && limbs[5] == 0);
+    (limbs[0] as u128) | ((limbs[1] as u128) << 64)
+}
+
+fn cmp6(a: &[u64; 6], b: &[u64; 6]) -> Ordering {
+    for i in (0..6).rev() {
+        match a[i].cmp(&b[i]) {
+            Ordering::Equal => continue,
+            other => return other,
+        }
+    }
+    Ordering::Equal
+}
+
+fn ge6(a: &[u64; 6], b: &[u64; 6]) -> bool {
+    cmp6(a, b) != Ordering::Less
+}
+
+fn sub6(a: &[u64; 6], b: &[u64; 6]) -> [u64; 6] {
+    let mut result = [0u64; 6];
+    let mut borrow = 0i128;
+    for i in 0..6 {
+        let diff = a[i] as i128 - b[i] as i128 - borrow;
+        if diff < 0 {
+            result[i] = (diff + (1i128 << 64)) as u64;
+            borrow = 1;
+        } else {
+            result[i] = diff as u64;
+            borrow = 0;
+        }
+    }
+    result
+}
+
+fn signed_sub6(a: &[u64; 6], b: &[u64; 6]) -> (bool, [u64; 6]) {
+    if ge6(a, b) {
+        (false, sub6(a, b))
+    } else {
+        (true, sub6(b, a))
+    }
+}
+
+fn add6_small(a: &mut [u64; 6], small: u64) {
+    let mut carry = small as u128;
+    for limb in a.iter_mut() {
+        if carry == 0 {
+            break;
+        }
+        let sum = *limb as u128 + carry;
+        *limb = sum as u64;
+        carry = sum >> 64;
+    }
+}
+
+fn shl1_6(a: &[u64; 6]) -> [u64; 6] {
+    let mut result = [0u64; 6];
+    let mut carry = 0u64;
+    for i in 0..6 {
+        result[i] = (a[i] << 1) | carry;
+        carry = a[i] >> 63;
+    }
+    result
+}
+
+/// `a (u128) * b (256-bit, little-endian limbs)`, widened to 384 bits.
+fn mul_u128_u256(a: u128, b: &[u64; 4]) -> [u64; 6] {
+    let a_limbs = [a as u64, (a >> 64) as u64];
+    let mut result = [0u64; 6];
+    for (i, &ai) in a_limbs.iter().enumerate() {
+        let mut carry = 0u128;
+        for (j, &bj) in b.iter().enumerate() {
+            let idx = i + j;
+            let prod = (ai as u128) * (bj as u128) + result[idx] as u128 + carry;
+            result[idx] = prod as u64;
+            carry = prod >> 64;
+        }
+        let mut idx = i + b.len();
+        while carry > 0 {
+            let sum = result[idx] as u128 + carry;
+            result[idx] = sum as u64;
+            carry = sum >> 64;
+            idx += 1;
+        }
+    }
+    result
+}
+
+/// Divide a 384-bit numerator by a 256-bit denominator via binary long
+/// division, returning `(quotient, remainder)`. The quotient is assumed (by
+/// callers in this module) to fit in the low two limbs.
+fn divmod_wide(numerator: [u64; 6], denom: &[u64; 4]) -> (u128, [u64; 4]) {
+    let denom_ext = zero_extend(denom);
+    let mut quotient = [0u64; 6];
+    let mut remainder = [0u64; 6];
+
+    for bit in (0..384).rev() {
+        let num_bit = (numerator[bit / 64] >> (bit % 64)) & 1;
+        remainder = shl1_6(&remainder);
+        remainder[0] |= num_bit;
+        if ge6(&remainder, &denom_ext) {
+            remainder = sub6(&remainder, &denom_ext);
+            quotient[bit / 64] |= 1 << (bit % 64);
+        }
+    }
+
+    let q = (quotient[0] as u128) | ((quotient[1] as u128) << 64);
+    let mut rem4 = [0u64; 4];
+    rem4.copy_from_slice(&remainder[..4]);
+    (q, rem4)
+}
+
+#[cfg(test)]
+mod glv_tests {
+    use super::*;
+    use ark_ec::AffineCurve;
+    use ark_std::{UniformRand, Zero};
+
+    fn signed_mul(p: G1Affine, sign: bool, k: u128) -> <G1Affine as AffineCurve>::Projective {
+        let scaled = p.mul(Fr::from(k));
+        if sign {
+            -scaled
+        } else {
+            scaled
+        }
+    }
+
+    #[test]
+    fn test_endomorphism_is_lambda_scaling() {
+        let mut rng = ark_std::test_rng();
+        let p = G1Affine::from(<G1Affine as AffineCurve>::Projective::rand(&mut rng));
+        let phi_p = endomorphism(&p);
+        assert_eq!(phi_p, G1Affine::from(p.mul(Fr::from(LAMBDA))));
+    }
+
+    #[test]
+    fn test_decompose_reconstructs_scalar() {
+        let mut rng = ark_std::test_rng();
+        for _ in 0..32 {
+            let k = Fr::rand(&mut rng);
+            let p = G1Affine::from(<G1Affine as AffineCurve>::Projective::rand(&mut rng));
+
+            let (sign1, k1, sign2, k2) = decompose(&k);
+
+            let lhs = p.mul(k);
+            let rhs = signed_mul(p, sign1, k1) + signed_mul(endomorphism(&p), sign2, k2);
+            assert_eq!(lhs, rhs);
+        }
+    }
+
+    #[test]
+    fn test_decompose_zero() {
+        let (sign1, k1, sign2, k2) = decompose(&Fr::zero());
+        assert_eq!((sign1, k1, sign2, k2), (false, 0, false, 0));
+    }
+}
@@ -0,0 +1,5 @@
+pub mod batch_adder;
+pub mod booth;
+pub mod collision_state;
+pub mod glv;
+pub mod msm;
@@ -0,0 +1,255 @@
+use crate::batch_adder::BatchAdder;
+use crate::booth::get_booth_index;
+use crate::collision_state::CollisionState;
+use ark_bls12_381::{g1, Fr, G1Affine, G1Projective};
+use ark_ec::ProjectiveCurve;
+use ark_ff::{BigInteger, PrimeField, Zero};
+
+// Pippenger's bucket method, wired on top of BatchAdder (for the additions)
+// and CollisionState (for scheduling them).
+//
+// Each window recodes its c-bit digit through the signed Booth recoding in
+// `crate::booth`, so there are only 2^(c-1) buckets (instead of 2^c) and a
+// negative digit is handled by negating the point before it's added in.
+// Because the recoding overlaps each window with the previous one's top
+// bit, there's one extra window beyond the plain `ceil(bits/c)` to carry
+// out the final overlap.
+//
+// Points are summed into their bucket in batches via BatchAdder. Two points
+// can't be added into the same bucket within one BatchAdder::batch_add call
+// - that's exactly a collision on the destination slice - so whenever a
+// point's bucket is already claimed by another point in the batch currently
+// being staged, it's deferred via CollisionState instead, and retried once
+// that batch (and therefore the bucket) is free again.
+
+/// Multi-scalar multiplication: `sum_i scalars[i] * points[i]`.
+pub fn msm(points: &[G1Affine], scalars: &[Fr]) -> G1Projective {
+    assert_eq!(points.len(), scalars.len(), "points and scalars must have the same length");
+
+    if points.is_empty() {
+        return G1Projective::zero();
+    }
+
+    let c = window_bits(points.len());
+    let num_windows = Fr::size_in_bits().div_ceil(c) + 1;
+    let scalar_bytes: Vec<Vec<u8>> = scalars.iter().map(|s| s.into_repr().to_bytes_le()).collect();
+
+    let mut batch_adder: BatchAdder<g1::Parameters> = BatchAdder::new(points.len());
+    let mut result = G1Projective::zero();
+    for w in (0..num_windows).rev() {
+        for _ in 0..c {
+            result.double_in_place();
+        }
+        result += process_window(points, &scalar_bytes, w, c, &mut batch_adder);
+    }
+    result
+}
+
+/// Parallel counterpart of `msm`: each window's bucket set only reads
+/// `points`/`scalar_bytes` and writes to its own freshly-allocated buckets,
+/// so windows are fully independent and get one rayon task each. Only the
+/// inter-window doublings, which thread one running total through every
+/// window, are combined serially afterwards.
+#[cfg(feature = "parallel")]
+pub fn msm_parallel(points: &[G1Affine], scalars: &[Fr]) -> G1Projective {
+    use rayon::prelude::*;
+
+    assert_eq!(points.len(), scalars.len(), "points and scalars must have the same length");
+
+    if points.is_empty() {
+        return G1Projective::zero();
+    }
+
+    let c = window_bits(points.len());
+    let num_windows = Fr::size_in_bits().div_ceil(c) + 1;
+    let scalar_bytes: Vec<Vec<u8>> = scalars.iter().map(|s| s.into_repr().to_bytes_le()).collect();
+
+    let window_sums: Vec<G1Projective> = (0..num_windows)
+        .into_par_iter()
+        .map(|w| {
+            let mut batch_adder: BatchAdder<g1::Parameters> = BatchAdder::new(points.len());
+            process_window(points, &scalar_bytes, w, c, &mut batch_adder)
+        })
+        .collect();
+
+    let mut result = G1Projective::zero();
+    for sum in window_sums.into_iter().rev() {
+        for _ in 0..c {
+            result.double_in_place();
+        }
+        result += sum;
+    }
+    result
+}
+
+/// Window size `c`, chosen from `ln(n)` the way `ark_ec`'s own variable-base
+/// MSM does: small inputs use a fixed small window, larger ones grow the
+/// window logarithmically so the number of buckets stays manageable.
+fn window_bits(n: usize) -> usize {
+    if n < 32 {
+        3
+    } else {
+        ln_without_floats(n).max(1)
+    }
+}
+
+fn ln_without_floats(n: usize) -> usize {
+    // ln(n) ~= log2(n) * ln(2), with ln(2) ~= 0.69315.
+    let log2_n = (usize::BITS - n.leading_zeros() - 1) as usize;
+    (log2_n * 69) / 100
+}
+
+/// Booth-recoded digit of `scalar_le_bytes` at window `window_index`, split
+/// into a bucket index (the digit's magnitude, in `1..=2^(c-1)`) and whether
+/// the point needs negating before it's added to that bucket. `None` means
+/// the digit is zero and the point contributes nothing to this window.
+fn booth_bucket(scalar_le_bytes: &[u8], window_index: usize, c: usize) -> Option<(usize, bool)> {
+    let digit = get_booth_index(window_index, c, scalar_le_bytes);
+    if digit == 0 {
+        None
+    } else {
+        Some((digit.unsigned_abs() as usize, digit < 0))
+    }
+}
+
+fn process_window(
+    points: &[G1Affine],
+    scalar_bytes: &[Vec<u8>],
+    window_index: usize,
+    c: usize,
+    batch_adder: &mut BatchAdder<g1::Parameters>,
+) -> G1Projective {
+    // Booth recoding gives digits of magnitude 1..=2^(c-1), so half as many
+    // buckets as a plain c-bit digit would need.
+    let num_buckets = (1usize << (c - 1)) + 1;
+    let mut buckets = vec![G1Affine::zero(); num_buckets];
+    let mut bucket_claimed = vec![false; num_buckets];
+
+    let mut batch_buckets: Vec<usize> = Vec::new();
+    let mut batch_dest: Vec<G1Affine> = Vec::new();
+    let mut batch_src: Vec<G1Affine> = Vec::new();
+
+    // Worst case every point but the first collides with bucket 1.
+    let mut collisions = CollisionState::new(points.len().max(1));
+
+    for (i, point) in points.iter().enumerate() {
+        let (bucket, negate) = match booth_bucket(&scalar_bytes[i], window_index, c) {
+            Some(digit) => digit,
+            None => continue,
+        };
+        if !bucket_claimed[bucket] {
+            bucket_claimed[bucket] = true;
+            batch_buckets.push(bucket);
+            batch_dest.push(buckets[bucket]);
+            batch_src.push(if negate { -*point } else { *point });
+        } else {
+            collisions.add_unprocessed(i as u32);
+        }
+    }
+
+    loop {
+        batch_adder.batch_add_fast(&mut batch_dest, &batch_src);
+        for (bucket, value) in batch_buckets.drain(..).zip(batch_dest.drain(..)) {
+            buckets[bucket] = value;
+            bucket_claimed[bucket] = false;
+        }
+        batch_src.clear();
+
+        if !collisions.needs_processing() {
+            break;
+        }
+
+        let tail = collisions.get_unprocessed_tail();
+        loop {
+            let entry = collisions.dequeue_unprocessed();
+            let point_index = collisions.get_entry_data(entry) as usize;
+            let (bucket, negate) = booth_bucket(&scalar_bytes[point_index], window_index, c)
+                .expect("only nonzero-digit points are ever queued as collisions");
+            if !bucket_claimed[bucket] {
+                collisions.mark_entry_processing(entry);
+                bucket_claimed[bucket] = true;
+                batch_buckets.push(bucket);
+                batch_dest.push(buckets[bucket]);
+                let point = points[point_index];
+                batch_src.push(if negate { -point } else { point });
+            } else {
+                collisions.mark_entry_unprocessed(entry);
+            }
+            if entry == tail {
+                break;
+            }
+        }
+        collisions.free_processing();
+    }
+
+    // Running-sum bucket reduction: sum_d (d * bucket[d]) via
+    // running_sum += bucket[d]; total += running_sum, from the top bucket down.
+    let mut running_sum = G1Projective::zero();
+    let mut total = G1Projective::zero();
+    for bucket in buckets.into_iter().skip(1).rev() {
+        running_sum.add_assign_mixed(&bucket);
+        total += running_sum;
+    }
+    total
+}
+
+#[cfg(test)]
+mod msm_tests {
+    use super::*;
+    use ark_ec::msm::VariableBaseMSM;
+    use ark_ec::AffineCurve;
+    use ark_std::UniformRand;
+
+    fn naive_msm(points: &[G1Affine], scalars: &[Fr]) -> G1Projective {
+        points
+            .iter()
+            .zip(scalars.iter())
+            .map(|(p, s)| p.mul(*s))
+            .fold(G1Projective::zero(), |acc, p| acc + p)
+    }
+
+    #[test]
+    fn test_msm_empty() {
+        assert_eq!(msm(&[], &[]), G1Projective::zero());
+    }
+
+    #[test]
+    fn test_msm_matches_naive_double_and_add() {
+        let mut rng = ark_std::test_rng();
+        for &n in &[1, 2, 5, 16, 33, 100] {
+            let points: Vec<G1Affine> = (0..n)
+                .map(|_| G1Affine::from(<G1Affine as AffineCurve>::Projective::rand(&mut rng)))
+                .collect();
+            let scalars: Vec<Fr> = (0..n).map(|_| Fr::rand(&mut rng)).collect();
+
+            assert_eq!(msm(&points, &scalars), naive_msm(&points, &scalars), "n={}", n);
+        }
+    }
+
+    #[test]
+    fn test_msm_matches_variable_base_msm() {
+        let mut rng = ark_std::test_rng();
+        let n = 64;
+        let points: Vec<G1Affine> = (0..n)
+            .map(|_| G1Affine::from(<G1Affine as AffineCurve>::Projective::rand(&mut rng)))
+            .collect();
+        let scalars: Vec<Fr> = (0..n).map(|_| Fr::rand(&mut rng)).collect();
+        let scalar_bigints: Vec<_> = scalars.iter().map(|s| s.into_repr()).collect();
+
+        let expected = VariableBaseMSM::multi_scalar_mul(&points, &scalar_bigints);
+        assert_eq!(msm(&points, &scalars), expected);
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_msm_parallel_matches_serial() {
+        let mut rng = ark_std::test_rng();
+        let n = 64;
+        let points: Vec<G1Affine> = (0..n)
+            .map(|_| G1Affine::from(<G1Affine as AffineCurve>::Projective::rand(&mut rng)))
+            .collect();
+        let scalars: Vec<Fr> = (0..n).map(|_| Fr::rand(&mut rng)).collect();
+
+        assert_eq!(super::msm_parallel(&points, &scalars), msm(&points, &scalars));
+    }
+}